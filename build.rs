@@ -0,0 +1,6 @@
+fn main() {
+    capnpc::CompilerCommand::new()
+        .file("schema/codera1d.capnp")
+        .run()
+        .expect("compiling codera1d.capnp schema");
+}
@@ -0,0 +1,173 @@
+use crate::{PersistMsg, RaidMap, RaidState};
+use anyhow::Result;
+use capnp::{message::ReaderOptions, serialize_packed};
+use chrono::Duration;
+use std::{
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+pub mod codera1d_capnp {
+    include!(concat!(env!("OUT_DIR"), "/codera1d_capnp.rs"));
+}
+
+use codera1d_capnp::{batch_request, batch_response};
+
+/// Values the listener needs from `Config`, copied out before `Config` is moved
+/// into Rocket's managed state.
+pub struct ListenerConfig {
+    pub api_key: String,
+    pub reservation_batch_size: usize,
+    pub reservation_ttl_secs: i64,
+}
+
+/// Spawn the Cap'n Proto batch listener. Each connection may carry a stream of
+/// `BatchRequest`s, each answered with a `BatchResponse`, so a worker can keep a
+/// socket open and pipeline reports. A request that fails (bad key, unknown
+/// raid) closes the connection without a response. The JSON routes are
+/// unaffected.
+pub fn spawn(
+    bind: String,
+    config: ListenerConfig,
+    state: RaidState,
+    dirty: mpsc::Sender<PersistMsg>,
+) {
+    let config = Arc::new(config);
+
+    thread::spawn(move || {
+        let listener = TcpListener::bind(&bind).expect("binding capnp listener");
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+
+            let config = Arc::clone(&config);
+            let state = Arc::clone(&state);
+            let dirty = dirty.clone();
+
+            thread::spawn(move || {
+                if let Err(err) = serve_connection(stream, &config, &state, &dirty) {
+                    eprintln!("capnp connection error: {}", err);
+                }
+            });
+        }
+    });
+}
+
+fn serve_connection(
+    stream: TcpStream,
+    config: &ListenerConfig,
+    state: &Mutex<RaidMap>,
+    dirty: &mpsc::Sender<PersistMsg>,
+) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    while let Some(message) =
+        serialize_packed::try_read_message(&mut reader, ReaderOptions::new())?
+    {
+        let request = message.get_root::<batch_request::Reader>()?;
+
+        let mut response = capnp::message::Builder::new_default();
+        handle_batch(request, config, state, dirty, &mut response)?;
+
+        serialize_packed::write_message(&mut writer, &response)?;
+    }
+
+    Ok(())
+}
+
+fn handle_batch(
+    request: batch_request::Reader,
+    config: &ListenerConfig,
+    state: &Mutex<RaidMap>,
+    dirty: &mpsc::Sender<PersistMsg>,
+    response: &mut capnp::message::Builder<capnp::message::HeapAllocator>,
+) -> Result<()> {
+    let raid_name = request.get_raid()?;
+    let api_key = request.get_api_key()?;
+    let reserve_count = request.get_reserve_count() as usize;
+
+    let mut raid_state = state.lock().unwrap();
+
+    let worker_id = authenticate(&raid_state, config, api_key)?;
+
+    // A count of 0 means "use the configured default"; anything else is
+    // clamped to that same default as an upper bound, so a worker can't send
+    // a huge count and have reserve_codes spin the loop (under the state
+    // lock) long after remaining_codes has run dry.
+    let count = if reserve_count == 0 {
+        config.reservation_batch_size
+    } else {
+        reserve_count.min(config.reservation_batch_size)
+    };
+
+    let (reservation, info) = {
+        let raid = raid_state
+            .raids
+            .get_mut(raid_name)
+            .ok_or_else(|| anyhow!("Raid not found"))?;
+
+        let tried_codes = request.get_tried_codes()?;
+        for i in 0..tried_codes.len() {
+            let code = tried_codes.get(i)?;
+            // A misconfigured worker on this high-volume path must not be able to
+            // panic the handler (which would drop the whole batch unanswered), so
+            // skip any code that isn't in the loaded dictionary.
+            if crate::raid::is_known_code(code) {
+                raid.try_code(code.to_owned());
+            }
+        }
+
+        let reservation = raid.reserve_codes(
+            count,
+            Duration::seconds(config.reservation_ttl_secs),
+            worker_id.clone(),
+        );
+
+        let info: crate::RaidInfo = (&*raid).into();
+
+        (reservation, info)
+    };
+
+    // Stamp worker liveness consistently with the JSON transport.
+    raid_state.touch_worker(&worker_id);
+
+    let mut builder = response.init_root::<batch_response::Builder>();
+    builder.set_remaining_code_count(info.remaining_code_count);
+    builder.set_tried_code_count(info.tried_code_count);
+
+    let mut codes = builder.init_reserved_codes(reservation.codes.len() as u32);
+    for (i, code) in reservation.codes.iter().enumerate() {
+        codes.set(i as u32, code.as_str());
+    }
+
+    // Mutations happened; flush through the same background writer as the JSON
+    // routes.
+    let _ = dirty.send(PersistMsg::Dirty);
+
+    Ok(())
+}
+
+/// Authenticate a batch request the same way the `ApiKey` guard does: the admin
+/// master key is accepted with no worker attribution, any registered token is
+/// accepted as that worker.
+fn authenticate(
+    raid_state: &RaidMap,
+    config: &ListenerConfig,
+    api_key: &str,
+) -> Result<Option<String>> {
+    if api_key == config.api_key {
+        return Ok(None);
+    }
+
+    if raid_state.workers.contains_key(api_key) {
+        return Ok(Some(api_key.to_owned()));
+    }
+
+    Err(anyhow!("Invalid API key"))
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+/// Runtime configuration, deserialized from a TOML file at startup. The path is
+/// taken from the `CODERA1D_CONFIG` environment variable, then the first
+/// positional CLI argument, falling back to `config.toml` in the working
+/// directory.
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    /// Address Rocket binds to.
+    pub address: String,
+    /// Port Rocket binds to.
+    pub port: u16,
+    /// Port the Cap'n Proto batch listener binds to, on the same address.
+    pub capnp_port: u16,
+    /// Admin master key, accepted by every guard and required by the token
+    /// management endpoints.
+    pub api_key: String,
+    /// Number of codes handed out per `reserve_codes` call.
+    pub reservation_batch_size: usize,
+    /// How long a reservation is held before `expire_reservations` reclaims it,
+    /// in seconds.
+    pub reservation_ttl_secs: i64,
+    /// Filesystem path to the code dictionary CSV, loaded at runtime.
+    pub pin_code_path: String,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Config> {
+        let contents = std::fs::read_to_string(path)?;
+        let config = toml::from_str(&contents)?;
+
+        Ok(config)
+    }
+}
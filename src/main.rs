@@ -5,11 +5,15 @@ extern crate rocket;
 #[macro_use]
 extern crate anyhow;
 
+mod capnp_endpoint;
+mod config;
 mod raid;
 
 use anyhow::Result;
-use lazy_static::lazy_static;
-use raid::{CodeReservation, Raid, RaidInfo};
+use chrono::{Duration, Utc};
+use config::Config;
+use rand::{distributions::Alphanumeric, Rng};
+use raid::{CodeReservation, Raid, RaidInfo, RaidView, Worker};
 use rocket::{
     outcome::IntoOutcome,
     request::{self, FromRequest, Request},
@@ -20,14 +24,23 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     env,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration as StdDuration,
 };
 
-lazy_static! {
-    static ref API_KEY: String = env::var("CODERA1D_API_KEY").unwrap();
-}
+/// Length of a generated worker token.
+const WORKER_TOKEN_LEN: usize = 40;
 
-struct ApiKey<'r>(&'r str);
+/// An authenticated caller. The admin master key (`Config::api_key`) is always
+/// accepted; any token registered in `RaidMap::workers` is accepted as that
+/// worker. `worker_id` is `None` for the admin key.
+struct ApiKey {
+    worker_id: Option<String>,
+}
 
 #[derive(Debug)]
 enum ApiKeyError {
@@ -35,17 +48,59 @@ enum ApiKeyError {
     Invalid,
 }
 
-impl<'a, 'r> FromRequest<'a, 'r> for ApiKey<'a> {
+impl<'a, 'r> FromRequest<'a, 'r> for ApiKey {
+    type Error = ApiKeyError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<ApiKey, ApiKeyError> {
+        let key = match request.headers().get_one("X-Api-Key") {
+            Some(key) => key,
+            None => {
+                return request::Outcome::Failure((
+                    rocket::http::Status::Forbidden,
+                    ApiKeyError::Missing,
+                ))
+            }
+        };
+
+        let config = request.guard::<State<Config>>().unwrap();
+
+        if key == config.api_key {
+            return request::Outcome::Success(ApiKey { worker_id: None });
+        }
+
+        // Read-only membership check: resolving the worker does not mutate
+        // state, so read-only routes don't dirty it just by authenticating.
+        // `last_seen` is stamped via `touch_worker` on the reserve and try-code
+        // paths (JSON and Cap'n Proto).
+        let state = request.guard::<State<RaidState>>().unwrap();
+        let registered = state.lock().unwrap().workers.contains_key(key);
+
+        if registered {
+            return request::Outcome::Success(ApiKey {
+                worker_id: Some(key.to_owned()),
+            });
+        }
+
+        Err(ApiKeyError::Invalid).into_outcome(rocket::http::Status::Forbidden)
+    }
+}
+
+/// Guard accepting only the admin master key, for token management endpoints.
+struct AdminKey;
+
+impl<'a, 'r> FromRequest<'a, 'r> for AdminKey {
     type Error = ApiKeyError;
 
-    fn from_request(request: &'a Request<'r>) -> request::Outcome<ApiKey<'a>, ApiKeyError> {
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<AdminKey, ApiKeyError> {
+        let config = request.guard::<State<Config>>().unwrap();
+
         request
             .headers()
             .get_one("X-Api-Key")
             .ok_or(ApiKeyError::Missing)
             .and_then(|key| {
-                if key == *API_KEY {
-                    Ok(ApiKey(key))
+                if key == config.api_key {
+                    Ok(AdminKey)
                 } else {
                     Err(ApiKeyError::Invalid)
                 }
@@ -56,27 +111,135 @@ impl<'a, 'r> FromRequest<'a, 'r> for ApiKey<'a> {
 
 type RaidState = Arc<Mutex<RaidMap>>;
 
+/// Shared liveness flag for the background writer. `true` means the last flush
+/// succeeded; it flips to `false` when a flush fails so the `/health` route can
+/// report the server can no longer persist state.
+type Health = Arc<AtomicBool>;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct RaidMap {
     raids: HashMap<String, Raid>,
+    /// Registered worker tokens keyed by token value.
+    #[serde(default)]
+    workers: HashMap<String, Worker>,
 }
 
 type PubRaidMap = HashMap<String, RaidInfo>;
 
+/// On-disk format version written by this binary. Version 0 is the header-less
+/// pre-versioning layout (`RaidMapV0`), sniffed by the absence of `FORMAT_MAGIC`;
+/// version 1 is the current shape. Bump this and append a `migrate_vN_to_vN1`
+/// step to `MIGRATIONS` whenever the serialized shape changes.
+const CURRENT_VERSION: u32 = 1;
+
+/// Marker at the head of every versioned `raids.bin`. Header-less files written
+/// by older builds (which began with a bincode map-length prefix) will not carry
+/// it, which is how `load` sniffs them as "version 0".
+const FORMAT_MAGIC: u64 = 0x52_41_49_44_5f_56_30_31; // "RAID_V01"
+
+/// Versioned envelope persisted to disk: the magic marker and format version,
+/// followed by the bincode-encoded `RaidMap` payload.
+#[derive(Serialize, Deserialize)]
+struct VersionedFile {
+    magic: u64,
+    format_version: u32,
+    payload: Vec<u8>,
+}
+
+/// Header-less top-level shape (format version 0), before the `workers` registry
+/// and `CodeReservation::worker_id` existed. Decoded into its own type because
+/// bincode is not self-describing and cannot default the missing trailing fields
+/// of the current shape.
+#[derive(Deserialize)]
+struct RaidMapV0 {
+    raids: HashMap<String, raid::RaidV0>,
+}
+
+impl From<RaidMapV0> for RaidMap {
+    fn from(old: RaidMapV0) -> RaidMap {
+        RaidMap {
+            raids: old
+                .raids
+                .into_iter()
+                .map(|(name, raid)| (name, raid.into()))
+                .collect(),
+            workers: HashMap::new(),
+        }
+    }
+}
+
+/// Ordered migration chain. `MIGRATIONS[n]` upgrades a `RaidMap` decoded from
+/// format version `n` to version `n + 1`; all steps from a file's version up to
+/// `CURRENT_VERSION` are applied in sequence. Version 0 is decoded from its own
+/// `RaidMapV0` shape (bincode cannot default appended fields) and mapped into the
+/// current shape before the chain runs, so the v0 step is the identity.
+const MIGRATIONS: &[fn(RaidMap) -> RaidMap] = &[migrate_v0_to_v1];
+
+fn migrate_v0_to_v1(map: RaidMap) -> RaidMap {
+    map
+}
+
 impl RaidMap {
     fn load() -> Result<RaidMap> {
         let raids_bin = std::fs::read("data/raids.bin")?;
-        let raid_map = bincode::deserialize_from(&raids_bin[..])?;
+        Self::from_bytes(&raids_bin)
+    }
+
+    /// Decode a `raids.bin` image, applying the migration chain up to the current
+    /// shape. The version is decoded into the struct shape it was written with,
+    /// then migrated forward one step at a time.
+    fn from_bytes(bytes: &[u8]) -> Result<RaidMap> {
+        let (version, mut map) = match bincode::deserialize::<VersionedFile>(bytes) {
+            Ok(file) if file.magic == FORMAT_MAGIC => {
+                if file.format_version > CURRENT_VERSION {
+                    return Err(anyhow!(
+                        "data/raids.bin is format version {} but this binary supports at most {}",
+                        file.format_version,
+                        CURRENT_VERSION
+                    ));
+                }
+
+                (
+                    file.format_version,
+                    bincode::deserialize::<RaidMap>(&file.payload)?,
+                )
+            }
+            // No magic marker: a header-less "version 0" file.
+            _ => (0, bincode::deserialize::<RaidMapV0>(bytes)?.into()),
+        };
+
+        for migrate in &MIGRATIONS[version as usize..] {
+            map = migrate(map);
+        }
 
-        Ok(raid_map)
+        Ok(map)
     }
 
-    fn save(&self) -> Result<()> {
-        let serialized = bincode::serialize(self)?;
+    /// Serialize the versioned on-disk image. Cheap enough to run under the
+    /// state lock; the (slower) disk write is done separately, unlocked.
+    fn serialize(&self) -> Result<Vec<u8>> {
+        let file = VersionedFile {
+            magic: FORMAT_MAGIC,
+            format_version: CURRENT_VERSION,
+            payload: bincode::serialize(self)?,
+        };
+
+        Ok(bincode::serialize(&file)?)
+    }
 
-        std::fs::write("data/raids.bin", serialized)?;
+    fn save(&self) -> Result<()> {
+        write_raids_bin(&self.serialize()?)
+    }
 
-        Ok(())
+    /// Stamp a worker's last-seen time. Called on the reserve and try-code
+    /// paths, where the state is being persisted anyway, so the update is never
+    /// silently lost.
+    fn touch_worker(&mut self, worker_id: &Option<String>) {
+        if let Some(id) = worker_id {
+            if let Some(worker) = self.workers.get_mut(id) {
+                worker.last_seen = Some(Utc::now());
+            }
+        }
     }
 
     fn to_pub_json(&self) -> Json<PubRaidMap> {
@@ -94,7 +257,142 @@ impl Default for RaidMap {
     fn default() -> Self {
         RaidMap {
             raids: HashMap::new(),
+            workers: HashMap::new(),
+        }
+    }
+}
+
+/// Monotonic counter making each temp file name unique within this process, so
+/// two writes can never collide on the same scratch path.
+static WRITE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Atomically replace `data/raids.bin` with `bytes` via a temp file + rename, so
+/// a crash mid-write cannot truncate the live file. The temp name is unique per
+/// write (pid + sequence) so concurrent writers never clobber each other's
+/// scratch file before the rename.
+fn write_raids_bin(bytes: &[u8]) -> Result<()> {
+    let seq = WRITE_SEQ.fetch_add(1, Ordering::Relaxed);
+    let tmp = format!("data/raids.bin.{}.{}.tmp", std::process::id(), seq);
+
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, "data/raids.bin")?;
+
+    Ok(())
+}
+
+/// How long the writer coalesces dirty signals before flushing to disk.
+const PERSIST_DEBOUNCE: StdDuration = StdDuration::from_millis(250);
+
+/// Message to the background writer thread. Every disk write goes through this
+/// one channel so writes are serialized — there is never more than one flush in
+/// flight, even across the request path, the Cap'n Proto listener, and shutdown.
+enum PersistMsg {
+    /// State changed and should be flushed (after the debounce window).
+    Dirty,
+    /// Flush one final time, then exit the process.
+    Shutdown,
+}
+
+/// Handle used by request handlers to signal that in-memory state has changed
+/// and should be persisted. Flushing happens on a background thread, so the
+/// request path never touches the disk.
+struct Persister {
+    tx: mpsc::Sender<PersistMsg>,
+}
+
+impl Persister {
+    fn mark_dirty(&self) {
+        // Best-effort: if the writer thread has gone away the state still lives
+        // in memory and there is nothing useful to report to the caller.
+        let _ = self.tx.send(PersistMsg::Dirty);
+    }
+
+    /// A clone of the signal sender, for mutators outside the request path
+    /// (e.g. the Cap'n Proto listener) and the shutdown handler.
+    fn sender(&self) -> mpsc::Sender<PersistMsg> {
+        self.tx.clone()
+    }
+}
+
+/// Serialize the current state under the lock, then release it and write the
+/// snapshot to disk unlocked, so request handlers are never blocked behind the
+/// disk write. Updates `health` so a persistent write failure is observable via
+/// the `/health` route rather than lost to stderr.
+fn flush(state: &RaidState, health: &Health) {
+    let snapshot = match state.lock().unwrap().serialize() {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            eprintln!("failed to serialize raids: {}", err);
+            health.store(false, Ordering::Relaxed);
+            return;
+        }
+    };
+
+    match write_raids_bin(&snapshot) {
+        Ok(()) => health.store(true, Ordering::Relaxed),
+        Err(err) => {
+            eprintln!("failed to persist raids: {}", err);
+            health.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Spawn the background writer and return a handle for signalling it. The writer
+/// debounces bursts of dirty signals into a single atomic write off the request
+/// path, and performs the final flush on `Shutdown` before exiting — so every
+/// write to `data/raids.bin` is made by this one thread.
+fn spawn_persister(state: RaidState, health: Health) -> Persister {
+    let (tx, rx) = mpsc::channel::<PersistMsg>();
+
+    thread::spawn(move || loop {
+        match rx.recv() {
+            Ok(PersistMsg::Dirty) => {
+                thread::sleep(PERSIST_DEBOUNCE);
+
+                // Coalesce any signals that piled up during the debounce, but
+                // honour a shutdown that arrived in the same window.
+                let mut shutdown = false;
+                while let Ok(msg) = rx.try_recv() {
+                    if let PersistMsg::Shutdown = msg {
+                        shutdown = true;
+                    }
+                }
+
+                flush(&state, &health);
+
+                if shutdown {
+                    std::process::exit(0);
+                }
+            }
+            Ok(PersistMsg::Shutdown) => {
+                flush(&state, &health);
+                std::process::exit(0);
+            }
+            Err(_) => break,
         }
+    });
+
+    Persister { tx }
+}
+
+/// Install a Ctrl-C / termination handler that asks the background writer to
+/// flush once more and exit. Rocket's blocking `launch()` never returns, so the
+/// writer's senders are never dropped on a signal — routing the final flush
+/// through the same thread keeps disk writes serialized instead of racing a
+/// direct flush from the signal handler.
+fn install_shutdown_flush(tx: mpsc::Sender<PersistMsg>) {
+    let result = ctrlc::set_handler(move || {
+        let _ = tx.send(PersistMsg::Shutdown);
+
+        // The writer thread flushes and exits the process; park here so the
+        // handler does not return and let startup continue past shutdown.
+        loop {
+            thread::sleep(StdDuration::from_secs(1));
+        }
+    });
+
+    if let Err(err) = result {
+        eprintln!("failed to install shutdown handler: {}", err);
     }
 }
 
@@ -103,6 +401,18 @@ fn index(_key: ApiKey) -> String {
     "Welcome to codera1d".to_owned()
 }
 
+/// Liveness probe for monitoring. Unauthenticated so a watchdog can poll it, it
+/// fails once the background writer has hit a persistent flush error, signalling
+/// that in-memory state is no longer reaching disk.
+#[get("/health")]
+fn health(health: State<Health>) -> Result<&'static str> {
+    if health.load(Ordering::Relaxed) {
+        Ok("ok")
+    } else {
+        Err(anyhow!("last persistence attempt failed"))
+    }
+}
+
 #[get("/raids")]
 fn raid_list(state: State<RaidState>, _key: ApiKey) -> Json<PubRaidMap> {
     let mut raid_state = state.lock().unwrap();
@@ -124,6 +434,7 @@ struct RaidReference {
 fn create_raid(
     form: Json<RaidReference>,
     state: State<RaidState>,
+    persister: State<Persister>,
     _key: ApiKey,
 ) -> Result<Json<PubRaidMap>> {
     let mut raid_state = state.lock().unwrap();
@@ -141,51 +452,132 @@ fn create_raid(
 
     raids.insert(form.name.clone(), new_raid.clone());
 
-    raid_state.save()?;
+    persister.mark_dirty();
 
     Ok(raid_state.to_pub_json())
 }
 
 #[delete("/raids", data = "<form>")]
-fn delete_raid(form: Json<RaidReference>, state: State<RaidState>, _key: ApiKey) -> Result<()> {
+fn delete_raid(
+    form: Json<RaidReference>,
+    state: State<RaidState>,
+    persister: State<Persister>,
+    _key: ApiKey,
+) -> Result<()> {
     let mut raid_state = state.lock().unwrap();
     let raids = &mut raid_state.raids;
 
     raids.remove(&form.name);
 
-    raid_state.save()?;
+    persister.mark_dirty();
 
     Ok(())
 }
 
 #[get("/raids/<name>")]
-fn get_raid(name: String, state: State<RaidState>, _key: ApiKey) -> Result<Json<Raid>> {
+fn get_raid(name: String, state: State<RaidState>, _key: ApiKey) -> Result<Json<RaidView>> {
     let mut raid_state = state.lock().unwrap();
     let raids = &mut raid_state.raids;
 
-    let raid = raids.get(&name).ok_or(anyhow!("Raid not found"))?.clone();
+    let raid = raids.get(&name).ok_or(anyhow!("Raid not found"))?;
 
-    Ok(Json(raid))
+    Ok(Json(raid.into()))
 }
 
 #[post("/raids/<name>/reserve_codes")]
 fn reserve_codes(
     name: String,
     state: State<RaidState>,
-    _key: ApiKey,
+    config: State<Config>,
+    persister: State<Persister>,
+    key: ApiKey,
 ) -> Result<Json<CodeReservation>> {
+    let worker_id = key.worker_id;
+
     let mut raid_state = state.lock().unwrap();
-    let raids = &mut raid_state.raids;
 
-    let raid = raids.get_mut(&name).ok_or(anyhow!("Raid not found"))?;
+    let code_reservation = {
+        let raid = raid_state
+            .raids
+            .get_mut(&name)
+            .ok_or(anyhow!("Raid not found"))?;
 
-    let code_reservation = raid.reserve_codes(5);
+        raid.reserve_codes(
+            config.reservation_batch_size,
+            Duration::seconds(config.reservation_ttl_secs),
+            worker_id.clone(),
+        )
+    };
 
-    raid_state.save()?;
+    raid_state.touch_worker(&worker_id);
+
+    persister.mark_dirty();
 
     Ok(Json(code_reservation))
 }
 
+#[derive(Deserialize)]
+struct WorkerReference {
+    label: String,
+}
+
+#[derive(Serialize)]
+struct WorkerToken {
+    token: String,
+}
+
+#[post("/workers", data = "<form>")]
+fn create_worker(
+    form: Json<WorkerReference>,
+    state: State<RaidState>,
+    persister: State<Persister>,
+    _key: AdminKey,
+) -> Result<Json<WorkerToken>> {
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(WORKER_TOKEN_LEN)
+        .collect();
+
+    let worker = Worker {
+        label: form.label.clone(),
+        created_at: Utc::now(),
+        last_seen: None,
+    };
+
+    let mut raid_state = state.lock().unwrap();
+    raid_state.workers.insert(token.clone(), worker);
+
+    persister.mark_dirty();
+
+    Ok(Json(WorkerToken { token }))
+}
+
+#[derive(Deserialize)]
+struct WorkerTokenReference {
+    token: String,
+}
+
+#[delete("/workers", data = "<form>")]
+fn revoke_worker(
+    form: Json<WorkerTokenReference>,
+    state: State<RaidState>,
+    persister: State<Persister>,
+    _key: AdminKey,
+) -> Result<()> {
+    let mut raid_state = state.lock().unwrap();
+
+    raid_state.workers.remove(&form.token);
+
+    raid_state
+        .raids
+        .iter_mut()
+        .for_each(|(_, raid)| raid.release_worker(&form.token));
+
+    persister.mark_dirty();
+
+    Ok(())
+}
+
 #[derive(Deserialize)]
 struct CodeInput {
     code: String,
@@ -196,37 +588,163 @@ fn try_code(
     name: String,
     form: Json<CodeInput>,
     state: State<RaidState>,
-    _key: ApiKey,
+    persister: State<Persister>,
+    key: ApiKey,
 ) -> Result<()> {
     let mut raid_state = state.lock().unwrap();
-    let raids = &mut raid_state.raids;
 
-    let raid = raids.get_mut(&name).ok_or(anyhow!("Raid not found"))?;
+    {
+        let raid = raid_state
+            .raids
+            .get_mut(&name)
+            .ok_or(anyhow!("Raid not found"))?;
+
+        // An unknown code would panic inside try_code via string_to_code_index,
+        // poisoning the state mutex for every other route. Reject it as a
+        // normal error instead. (The Cap'n Proto path uses the same check but
+        // skips unknown codes rather than failing the request.)
+        if !raid::is_known_code(&form.code) {
+            return Err(anyhow!("Unknown code"));
+        }
+
+        raid.try_code(form.code.clone());
+    }
+
+    raid_state.touch_worker(&key.worker_id);
+
+    persister.mark_dirty();
+
+    Ok(())
+}
 
-    raid.try_code(form.code.clone());
+/// Load, migrate, and re-save `data/raids.bin` without launching the server, so
+/// operators can batch-upgrade stored raids to the current format between
+/// releases.
+fn upgrade() -> Result<()> {
+    let raid_map = RaidMap::load()?;
+    raid_map.save()?;
 
-    raid_state.save()?;
+    println!(
+        "Upgraded data/raids.bin to format version {} ({} raids)",
+        CURRENT_VERSION,
+        raid_map.raids.len()
+    );
 
     Ok(())
 }
 
+/// Path to the TOML config file, from the `CODERA1D_CONFIG` env var or a
+/// positional CLI argument, defaulting to `config.toml`.
+fn config_path() -> String {
+    env::var("CODERA1D_CONFIG")
+        .ok()
+        .or_else(|| env::args().nth(1))
+        .unwrap_or_else(|| "config.toml".to_owned())
+}
+
+/// Load `data/raids.bin`, falling back to an empty map *only* when the file does
+/// not yet exist. Any other load failure (corrupt data, an unsupported future
+/// version, a legacy decode error) aborts startup rather than silently starting
+/// empty and letting the persister overwrite real data.
+fn load_or_default() -> RaidMap {
+    match RaidMap::load() {
+        Ok(raid_map) => raid_map,
+        Err(err) => {
+            if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
+                if io_err.kind() == std::io::ErrorKind::NotFound {
+                    return RaidMap::default();
+                }
+            }
+
+            panic!("failed to load data/raids.bin: {}", err);
+        }
+    }
+}
+
 fn main() {
-    let raid_map = RaidMap::load().unwrap_or_default();
-    let raid_state: RaidState = Arc::new(Mutex::new(raid_map));
+    if env::args().nth(1).as_deref() == Some("upgrade") {
+        upgrade().unwrap();
+        return;
+    }
+
+    let config = Config::load(&config_path()).unwrap();
+    raid::load_pin_codes(&config.pin_code_path).unwrap();
 
-    rocket::ignite()
+    let raid_map = load_or_default();
+    let raid_state: RaidState = Arc::new(Mutex::new(raid_map));
+    let health: Health = Arc::new(AtomicBool::new(true));
+
+    let persister = spawn_persister(Arc::clone(&raid_state), Arc::clone(&health));
+    install_shutdown_flush(persister.sender());
+
+    capnp_endpoint::spawn(
+        format!("{}:{}", config.address, config.capnp_port),
+        capnp_endpoint::ListenerConfig {
+            api_key: config.api_key.clone(),
+            reservation_batch_size: config.reservation_batch_size,
+            reservation_ttl_secs: config.reservation_ttl_secs,
+        },
+        Arc::clone(&raid_state),
+        persister.sender(),
+    );
+
+    let rocket_config = rocket::Config::build(rocket::config::Environment::active().unwrap())
+        .address(config.address.clone())
+        .port(config.port)
+        .finalize()
+        .unwrap();
+
+    rocket::custom(rocket_config)
         .manage(raid_state)
+        .manage(config)
+        .manage(persister)
+        .manage(health)
         .mount(
             "/",
             routes![
                 index,
+                health,
                 raid_list,
                 get_raid,
                 create_raid,
                 delete_raid,
                 reserve_codes,
-                try_code
+                try_code,
+                create_worker,
+                revoke_worker
             ],
         )
         .launch();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_header_less_v0_image() {
+        // A pre-versioning file is a bare bincode map with no `workers` field and
+        // reservations that lack `worker_id` — reproduce that layout here.
+        #[derive(Serialize)]
+        struct V0Image {
+            raids: HashMap<String, Raid>,
+        }
+
+        let mut raid = Raid::new();
+        raid.skip_codes(1000);
+
+        let mut raids = HashMap::new();
+        raids.insert("legacy".to_owned(), raid);
+
+        let bytes = bincode::serialize(&V0Image { raids }).unwrap();
+
+        let map = RaidMap::from_bytes(&bytes).expect("header-less v0 image should load");
+
+        assert_eq!(map.raids.len(), 1);
+        assert!(map.workers.is_empty());
+
+        let info: RaidInfo = (&map.raids["legacy"]).into();
+        assert_eq!(info.tried_code_count, 1000);
+        assert_eq!(info.remaining_code_count, 9000);
+    }
+}
@@ -1,5 +1,9 @@
-use chrono::{serde::ts_seconds, DateTime, Duration, Utc};
-use lazy_static::lazy_static;
+use anyhow::Result;
+use chrono::{
+    serde::{ts_seconds, ts_seconds_option},
+    DateTime, Duration, Utc,
+};
+use once_cell::sync::OnceCell;
 use roaring::RoaringBitmap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
@@ -12,6 +16,19 @@ pub struct CodeReservation {
     pub codes: Vec<String>,
     #[serde(with = "ts_seconds")]
     pub expires_at: DateTime<Utc>,
+    /// Token of the worker that holds this reservation, or `None` for
+    /// reservations made directly with the admin master key.
+    #[serde(default)]
+    pub worker_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Worker {
+    pub label: String,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "ts_seconds_option")]
+    pub last_seen: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,18 +48,121 @@ pub struct RaidInfo {
     pub tried_code_count: u32,
 }
 
-lazy_static! {
-    static ref PIN_CODE_LIST: Vec<String> = include_str!("pin_codes.csv")
+/// Client-facing view of a `CodeReservation` with the worker token omitted.
+/// The token doubles as that worker's bearer credential, so echoing it back on
+/// a read path (e.g. `GET /raids/<name>`) would let any registered worker read
+/// another worker's token off a reservation and impersonate it.
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeReservationView {
+    pub codes: Vec<String>,
+    #[serde(with = "ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<&CodeReservation> for CodeReservationView {
+    fn from(reservation: &CodeReservation) -> CodeReservationView {
+        CodeReservationView {
+            codes: reservation.codes.clone(),
+            expires_at: reservation.expires_at,
+        }
+    }
+}
+
+/// Client-facing view of a `Raid`, with reservations redacted via
+/// `CodeReservationView`. Returned by read routes instead of `Raid` itself.
+#[derive(Debug, Clone, Serialize)]
+pub struct RaidView {
+    #[serde(serialize_with = "bitmap_to_bytes")]
+    remaining_codes: RoaringBitmap,
+    #[serde(serialize_with = "bitmap_to_bytes")]
+    tried_codes: RoaringBitmap,
+    code_reservations: Vec<CodeReservationView>,
+}
+
+impl From<&Raid> for RaidView {
+    fn from(raid: &Raid) -> RaidView {
+        RaidView {
+            remaining_codes: raid.remaining_codes.clone(),
+            tried_codes: raid.tried_codes.clone(),
+            code_reservations: raid.code_reservations.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// On-disk shape of `CodeReservation` before the `worker_id` field existed
+/// (format version 0). Kept so legacy `raids.bin` files can be decoded
+/// into their original shape and then mapped forward — bincode is not
+/// self-describing, so a missing trailing field cannot be defaulted on the wire.
+#[derive(Deserialize)]
+pub struct CodeReservationV0 {
+    pub codes: Vec<String>,
+    #[serde(with = "ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+}
+
+/// On-disk shape of `Raid` for format version 0. The bitmap encoding is
+/// unchanged; only the reservation shape differs.
+#[derive(Deserialize)]
+pub struct RaidV0 {
+    #[serde(deserialize_with = "bitmap_from_bytes")]
+    remaining_codes: RoaringBitmap,
+    #[serde(deserialize_with = "bitmap_from_bytes")]
+    tried_codes: RoaringBitmap,
+    code_reservations: Vec<CodeReservationV0>,
+}
+
+impl From<CodeReservationV0> for CodeReservation {
+    fn from(old: CodeReservationV0) -> CodeReservation {
+        CodeReservation {
+            codes: old.codes,
+            expires_at: old.expires_at,
+            worker_id: None,
+        }
+    }
+}
+
+impl From<RaidV0> for Raid {
+    fn from(old: RaidV0) -> Raid {
+        Raid {
+            remaining_codes: old.remaining_codes,
+            tried_codes: old.tried_codes,
+            code_reservations: old.code_reservations.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Active code dictionary. Populated once at startup by `load_pin_codes`, or
+/// lazily from the compiled-in default the first time a lookup runs without a
+/// runtime list (e.g. in tests).
+static PIN_CODE_LIST: OnceCell<Vec<String>> = OnceCell::new();
+
+fn parse_pin_codes(contents: &str) -> Vec<String> {
+    contents
         .lines()
         .flat_map(|line| line.split(";").next())
         .map(str::to_owned)
-        .collect();
+        .collect()
+}
+
+fn pin_code_list() -> &'static Vec<String> {
+    PIN_CODE_LIST.get_or_init(|| parse_pin_codes(include_str!("pin_codes.csv")))
+}
+
+/// Load the active code dictionary from a CSV file, replacing the compiled-in
+/// default. Must be called before any code lookups; errors if a list has
+/// already been loaded.
+pub fn load_pin_codes(path: &str) -> Result<()> {
+    let contents = std::fs::read_to_string(path)?;
+
+    PIN_CODE_LIST
+        .set(parse_pin_codes(&contents))
+        .map_err(|_| anyhow!("code dictionary already loaded"))
 }
 
 impl Raid {
     pub fn new() -> Raid {
         let mut bitmap = RoaringBitmap::new();
-        bitmap.insert_range(0..10_000);
+        bitmap.insert_range(0..pin_code_list().len() as u32);
 
         Raid {
             remaining_codes: bitmap,
@@ -70,7 +190,12 @@ impl Raid {
             .bitor_assign(RoaringBitmap::from_iter(codes_to_retry));
     }
 
-    pub fn reserve_codes(&mut self, count: usize) -> CodeReservation {
+    pub fn reserve_codes(
+        &mut self,
+        count: usize,
+        ttl: Duration,
+        worker_id: Option<String>,
+    ) -> CodeReservation {
         let mut codes = Vec::new();
 
         for _ in 0..count {
@@ -82,15 +207,39 @@ impl Raid {
 
         codes.reverse();
 
-        let expires_at = Utc::now() + Duration::minutes(1);
+        let expires_at = Utc::now() + ttl;
 
-        let reservation = CodeReservation { codes, expires_at };
+        let reservation = CodeReservation {
+            codes,
+            expires_at,
+            worker_id,
+        };
 
         self.code_reservations.push(reservation.clone());
 
         reservation
     }
 
+    /// Release every outstanding, untried reservation held by `worker_id` back
+    /// into `remaining_codes`, using the same logic as `expire_reservations`.
+    /// Used to reclaim in-flight ranges when a worker token is revoked.
+    pub fn release_worker(&mut self, worker_id: &str) {
+        let released_reservations = self
+            .code_reservations
+            .drain_filter(|reservation| reservation.worker_id.as_deref() == Some(worker_id))
+            .collect::<Vec<_>>();
+
+        let codes_to_retry: Vec<u32> = released_reservations
+            .into_iter()
+            .flat_map(|reservation| reservation.codes)
+            .filter(|code| !self.tried_codes.contains(string_to_code_index(code)))
+            .map(|code| string_to_code_index(&code))
+            .collect();
+
+        self.remaining_codes
+            .bitor_assign(RoaringBitmap::from_iter(codes_to_retry));
+    }
+
     pub fn try_code(&mut self, code: String) {
         let code_idx = string_to_code_index(&code);
         self.remaining_codes.remove(code_idx);
@@ -111,7 +260,7 @@ impl Into<RaidInfo> for &Raid {
         let tried_codes = self.tried_codes.len() as u32;
         RaidInfo {
             tried_code_count: tried_codes,
-            remaining_code_count: 10000 - tried_codes,
+            remaining_code_count: pin_code_list().len() as u32 - tried_codes,
         }
     }
 }
@@ -135,12 +284,19 @@ where
     s.serialize_str(&base64::encode(&bytes))
 }
 
+/// Whether `code` exists in the loaded dictionary. Callers on untrusted paths
+/// must check this before `try_code`, which panics via `string_to_code_index`
+/// on an unknown code.
+pub fn is_known_code(code: &str) -> bool {
+    pin_code_list().iter().any(|known| known == code)
+}
+
 fn code_index_to_string(code_idx: u32) -> &'static String {
-    PIN_CODE_LIST.get(code_idx as usize).unwrap()
+    pin_code_list().get(code_idx as usize).unwrap()
 }
 
 fn string_to_code_index(code_str: &str) -> u32 {
-    PIN_CODE_LIST
+    pin_code_list()
         .iter()
         .enumerate()
         .find(|(_, code)| *code == code_str)
@@ -186,7 +342,7 @@ mod tests {
     #[test]
     fn reserve_codes() {
         let mut raid = Raid::new();
-        let reservation = raid.reserve_codes(5);
+        let reservation = raid.reserve_codes(5, Duration::minutes(1), None);
         assert_eq!(raid.tried_codes.len(), 0);
         assert_eq!(raid.remaining_codes.len(), 9_995);
         assert_eq!(
@@ -198,7 +354,7 @@ mod tests {
     #[test]
     fn expire_reservations_untried() {
         let mut raid = Raid::new();
-        let mut reservation = raid.reserve_codes(5);
+        let mut reservation = raid.reserve_codes(5, Duration::minutes(1), None);
         reservation.expires_at = Utc::now() - Duration::minutes(1);
         raid.code_reservations = vec![reservation];
         raid.expire_reservations();
@@ -210,7 +366,7 @@ mod tests {
     #[test]
     fn expire_reservations_tried() {
         let mut raid = Raid::new();
-        let mut reservation = raid.reserve_codes(5);
+        let mut reservation = raid.reserve_codes(5, Duration::minutes(1), None);
         raid.try_code(reservation.codes.pop().unwrap());
         reservation.expires_at = Utc::now() - Duration::minutes(1);
         raid.code_reservations = vec![reservation];
@@ -220,6 +376,29 @@ mod tests {
         assert_eq!(raid.remaining_codes.len(), 9_999);
     }
 
+    #[test]
+    fn release_worker_untried() {
+        let mut raid = Raid::new();
+        let reservation = raid.reserve_codes(5, Duration::minutes(1), Some(String::from("worker-a")));
+        raid.code_reservations = vec![reservation];
+        raid.release_worker("worker-a");
+
+        assert_eq!(raid.tried_codes.len(), 0);
+        assert_eq!(raid.remaining_codes.len(), 10_000);
+        assert!(raid.code_reservations.is_empty());
+    }
+
+    #[test]
+    fn release_worker_leaves_other_workers() {
+        let mut raid = Raid::new();
+        raid.reserve_codes(5, Duration::minutes(1), Some(String::from("worker-a")));
+        raid.reserve_codes(5, Duration::minutes(1), Some(String::from("worker-b")));
+        raid.release_worker("worker-a");
+
+        assert_eq!(raid.remaining_codes.len(), 9_995);
+        assert_eq!(raid.code_reservations.len(), 1);
+    }
+
     #[test]
     fn test_code_index_to_string() {
         assert_eq!(code_index_to_string(0), "1234");